@@ -0,0 +1,49 @@
+use macroquad::miniquad::conf::Icon;
+use macroquad::prelude::*;
+
+pub const GAME_TITLE: &str = "Black and Gold";
+
+const WINDOW_WIDTH: i32 = 800;
+const WINDOW_HEIGHT: i32 = 600;
+const HIGH_DPI: bool = false;
+
+// Pre-sized from ios/app-store-assets/AppIcon.png. Replace these files to change
+// the window icon.
+const ICON_SMALL: &[u8] = include_bytes!("../assets/icons/icon-16x16.png");
+const ICON_MEDIUM: &[u8] = include_bytes!("../assets/icons/icon-32x32.png");
+const ICON_BIG: &[u8] = include_bytes!("../assets/icons/icon-64x64.png");
+
+fn decode_icon(png: &[u8], pixels: &mut [u8]) {
+    let image = Image::from_file_with_format(png, Some(ImageFormat::Png))
+        .expect("Embedded icon is not a valid PNG");
+    assert_eq!(
+        image.bytes.len(),
+        pixels.len(),
+        "Embedded icon has the wrong size"
+    );
+    pixels.copy_from_slice(&image.bytes);
+}
+
+fn icon() -> Icon {
+    let mut icon = Icon {
+        small: [0; 16 * 16 * 4],
+        medium: [0; 32 * 32 * 4],
+        big: [0; 64 * 64 * 4],
+    };
+    decode_icon(ICON_SMALL, &mut icon.small);
+    decode_icon(ICON_MEDIUM, &mut icon.medium);
+    decode_icon(ICON_BIG, &mut icon.big);
+    icon
+}
+
+pub fn window_conf() -> Conf {
+    Conf {
+        window_title: GAME_TITLE.to_string(),
+        window_width: WINDOW_WIDTH,
+        window_height: WINDOW_HEIGHT,
+        high_dpi: HIGH_DPI,
+        icon: Some(icon()),
+        // sample_count: 4,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,86 @@
+use macroquad::prelude::*;
+
+use crate::{crash, simple_logger};
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn report_json(seed: u64, screenshot: Option<&str>) -> String {
+    let log_tail = simple_logger::recent_records()
+        .iter()
+        .map(|line| json_string(line))
+        .collect::<Vec<_>>()
+        .join(",\n    ");
+    format!(
+        "{{\n  \"seed\": {},\n  \"game\": {},\n  \"platform\": {},\n  \"screen\": \"{}x{}\",\n  \"screenshot\": {},\n  \"log\": [\n    {}\n  ]\n}}\n",
+        seed,
+        json_string(&crash::game_summary()),
+        json_string(&format!(
+            "{} {}",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        )),
+        screen_width(),
+        screen_height(),
+        screenshot.map_or("null".to_string(), json_string),
+        log_tail,
+    )
+}
+
+/// Saves a screenshot and a JSON report for attaching to bug reports. On WASM
+/// the JSON is copied to the clipboard instead. Returns a message for the player.
+pub fn capture(seed: u64) -> String {
+    #[cfg(target_arch = "wasm32")]
+    {
+        miniquad::window::clipboard_set(&report_json(seed, None));
+        "Bug report copied to clipboard".to_string()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let name = format!("bug-report-{}", (miniquad::date::now() * 1000.0) as u64);
+        let path = format!("{}.json", name);
+        // export_png unwraps its write, so make sure the file can be created first
+        let screenshot = format!("{}.png", name);
+        let screenshot_error = std::fs::File::create(&screenshot).err();
+        if let Some(e) = &screenshot_error {
+            log::error!("Could not write screenshot to {}: {}", screenshot, e);
+        }
+        let json = report_json(
+            seed,
+            screenshot_error.is_none().then_some(screenshot.as_str()),
+        );
+
+        if let Err(e) = std::fs::write(&path, json) {
+            log::error!("Could not write bug report to {}: {}", path, e);
+            if screenshot_error.is_none() {
+                let _ = std::fs::remove_file(&screenshot);
+            }
+            return format!("Could not save bug report: {}", e);
+        }
+        log::info!("Bug report written to {}", path);
+
+        match screenshot_error {
+            None => {
+                get_screen_data().export_png(&screenshot);
+                format!("Bug report saved to {}", path)
+            }
+            Some(e) => format!("Bug report saved to {} (no screenshot: {})", path, e),
+        }
+    }
+}
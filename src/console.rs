@@ -0,0 +1,45 @@
+use log::{Level, LevelFilter};
+use macroquad::prelude::*;
+
+use crate::simple_logger;
+
+const CONSOLE_LINES: usize = 12;
+
+/// Steps the game's own log level through info, debug and trace.
+pub fn cycle_log_level() {
+    let module = env!("CARGO_CRATE_NAME");
+    let level = match simple_logger::level_for(module) {
+        LevelFilter::Info => LevelFilter::Debug,
+        LevelFilter::Debug => LevelFilter::Trace,
+        _ => LevelFilter::Info,
+    };
+    simple_logger::set_module_level(module, level);
+    log::info!("Log level for {} set to {}", module, level);
+}
+
+pub fn draw_log_console() {
+    let font_size = 16;
+    let line_height = font_size as f32;
+    draw_rectangle(
+        0.0,
+        0.0,
+        screen_width(),
+        CONSOLE_LINES as f32 * line_height + 8.0,
+        Color::new(0.0, 0.0, 0.0, 0.75),
+    );
+    for (i, line) in simple_logger::tail(CONSOLE_LINES).iter().enumerate() {
+        let color = match line.level {
+            Level::Error => RED,
+            Level::Warn => ORANGE,
+            Level::Info => WHITE,
+            Level::Debug | Level::Trace => GRAY,
+        };
+        draw_text(
+            &line.to_string(),
+            4.0,
+            (i + 1) as f32 * line_height,
+            font_size as f32,
+            color,
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::backtrace::Backtrace;
+use std::fmt::Display;
+use std::panic;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+use crate::simple_logger;
+
+lazy_static! {
+    static ref GAME_SUMMARY: Mutex<String> = Mutex::new(String::new());
+}
+
+/// Updated every frame so a crash report can say what the game was doing.
+pub fn set_game_summary(summary: String) {
+    if let Ok(mut game_summary) = GAME_SUMMARY.try_lock() {
+        *game_summary = summary;
+    }
+}
+
+pub fn game_summary() -> String {
+    match GAME_SUMMARY.try_lock() {
+        Ok(summary) => summary.clone(),
+        Err(_) => "unavailable".to_string(),
+    }
+}
+
+fn crash_report(panic: &dyn Display) -> String {
+    format!(
+        "{}\n\nGame: {}\n\nBacktrace:\n{}\n\nRecent log:\n{}\n",
+        panic,
+        game_summary(),
+        Backtrace::force_capture(),
+        simple_logger::recent_records().join("\n"),
+    )
+}
+
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = crash_report(info);
+
+        #[cfg(target_arch = "wasm32")]
+        macroquad::logging::error!("{}", report);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = format!("crash-{}.txt", macroquad::miniquad::date::now() as u64);
+            match std::fs::write(&path, &report) {
+                Ok(()) => eprintln!("Crash report written to {}", path),
+                Err(e) => eprintln!("Could not write crash report to {}: {}", path, e),
+            }
+        }
+
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,46 @@
+use macroquad::prelude::*;
+
+const SLOW_MOTION_SCALE: f32 = 0.1;
+
+/// Debug-build controls for inspecting collisions: F1 toggles 10% speed,
+/// F2 pauses the simulation and F3 advances it by one step while paused.
+#[derive(Default)]
+pub struct DebugTime {
+    paused: bool,
+    slow_motion: bool,
+    step: bool,
+}
+
+impl DebugTime {
+    pub fn update(&mut self) {
+        if is_key_pressed(KeyCode::F1) {
+            self.slow_motion = !self.slow_motion;
+        }
+        if is_key_pressed(KeyCode::F2) {
+            self.paused = !self.paused;
+        }
+        self.step = self.paused && is_key_pressed(KeyCode::F3);
+    }
+
+    /// The frame time to simulate, or `None` if the simulation should not advance.
+    /// Stepping always advances exactly one simulation step, whatever the frame rate.
+    pub fn scale(&self, frame_time: f32) -> Option<f32> {
+        if self.paused {
+            return self.step.then_some(crate::MAX_FRAME_TIME);
+        }
+        if self.slow_motion {
+            Some(frame_time * SLOW_MOTION_SCALE)
+        } else {
+            Some(frame_time)
+        }
+    }
+
+    pub fn draw_status(&self) {
+        let text = match (self.paused, self.slow_motion) {
+            (false, false) => return,
+            (true, _) => "PAUSED (F2 resume, F3 step)",
+            (false, true) => "SLOW MOTION 10%",
+        };
+        draw_text(text, 4.0, screen_height() - 4.0, 18.0, RED);
+    }
+}
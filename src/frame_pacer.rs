@@ -0,0 +1,65 @@
+#[derive(Clone, Copy, PartialEq)]
+pub enum FrameCap {
+    Fps30,
+    Fps60,
+    Fps120,
+    Uncapped,
+}
+
+impl FrameCap {
+    pub fn fps(self) -> Option<f64> {
+        match self {
+            FrameCap::Fps30 => Some(30.0),
+            FrameCap::Fps60 => Some(60.0),
+            FrameCap::Fps120 => Some(120.0),
+            FrameCap::Uncapped => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            FrameCap::Fps30 => FrameCap::Fps60,
+            FrameCap::Fps60 => FrameCap::Fps120,
+            FrameCap::Fps120 => FrameCap::Uncapped,
+            FrameCap::Uncapped => FrameCap::Fps30,
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self.fps() {
+            Some(fps) => format!("{}", fps),
+            None => "Max".to_string(),
+        }
+    }
+}
+
+/// Sleeps out the rest of the frame, since macroquad doesn't cap the frame rate
+/// when vsync is off. Browsers pace frames themselves, so this is a no-op on WASM.
+pub struct FramePacer {
+    #[cfg(not(target_arch = "wasm32"))]
+    last_frame: std::time::Instant,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            last_frame: std::time::Instant::now(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait(&mut self, fps: Option<f64>) {
+        if let Some(fps) = fps {
+            let frame_time = std::time::Duration::from_secs_f64(1.0 / fps);
+            let elapsed = self.last_frame.elapsed();
+            if elapsed < frame_time {
+                std::thread::sleep(frame_time - elapsed);
+            }
+        }
+        self.last_frame = std::time::Instant::now();
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn wait(&mut self, _fps: Option<f64>) {}
+}
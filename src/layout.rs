@@ -0,0 +1,55 @@
+use macroquad::prelude::*;
+
+// The window size the hard-coded font sizes and margins were tuned for
+const REFERENCE_WIDTH: f32 = 800.0;
+const REFERENCE_HEIGHT: f32 = 600.0;
+
+/// How much to grow or shrink text and margins for the current window.
+pub fn ui_scale() -> f32 {
+    f32::min(
+        screen_width() / REFERENCE_WIDTH,
+        screen_height() / REFERENCE_HEIGHT,
+    )
+    .clamp(0.6, 2.0)
+}
+
+pub fn scaled(size: f32) -> f32 {
+    size * ui_scale()
+}
+
+pub fn scaled_font(font_size: u16) -> u16 {
+    (font_size as f32 * ui_scale()).round() as u16
+}
+
+pub fn percent_of_width(percent: f32) -> f32 {
+    screen_width() * percent / 100.0
+}
+
+/// Lays `widths` out left to right, centered on `center_x` with `spacing` between
+/// items. Returns each item's left x and the factor the row was shrunk by to fit
+/// into `max_width` (1.0 if it already fit).
+pub fn centered_row(
+    widths: &[f32],
+    spacing: f32,
+    center_x: f32,
+    max_width: f32,
+) -> (Vec<f32>, f32) {
+    let total = widths.iter().sum::<f32>() + spacing * widths.len().saturating_sub(1) as f32;
+    let fit = if total > max_width {
+        max_width / total
+    } else {
+        1.0
+    };
+
+    let mut x = center_x - total * fit / 2.0;
+    let xs = widths
+        .iter()
+        .map(|width| {
+            let left = x;
+            x += (width + spacing) * fit;
+            left
+        })
+        .collect();
+
+    (xs, fit)
+}
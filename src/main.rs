@@ -1,8 +1,24 @@
+mod branding;
+mod bug_report;
+#[cfg(debug_assertions)]
+mod console;
+mod crash;
+#[cfg(debug_assertions)]
+mod debug_time;
+mod frame_pacer;
 mod ios;
+mod layout;
+mod mods;
+mod recorder;
+mod simple_logger;
 
 use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
 
-use macroquad::audio::{load_sound, play_sound, set_sound_volume, PlaySoundParams, Sound};
+use macroquad::audio::{
+    load_sound, load_sound_from_bytes, play_sound, set_sound_volume, stop_sound, PlaySoundParams,
+    Sound,
+};
 
 use macroquad::experimental::collections::storage;
 use macroquad::prelude::*;
@@ -11,9 +27,30 @@ use macroquad::experimental::coroutines::start_coroutine;
 
 use itertools::Itertools;
 
-mod simple_logger;
+use branding::window_conf;
+use frame_pacer::{FrameCap, FramePacer};
+use recorder::FrameRecorder;
 
 const MOVEMENT_SPEED: f32 = 3.3;
+const MAX_FRAME_TIME: f32 = 0.0035;
+// Balls used to move one MAX_FRAME_TIME step per rendered frame, so their speed
+// followed the frame rate. Simulated time now advances at the rate that gave on a
+// 60 Hz display, split into steps of at most MAX_FRAME_TIME.
+const REFERENCE_FPS: f32 = 60.0;
+const SIMULATION_RATE: f32 = MAX_FRAME_TIME * REFERENCE_FPS;
+// Don't try to catch up on more than this after a stall
+const MAX_REAL_FRAME_TIME: f32 = 0.1;
+
+const SPLASH_DURATION: f32 = 2.5;
+const TITLE_REVEAL_DURATION: f32 = 1.0;
+const TRANSITION_DURATION: f32 = 0.4;
+
+const BATTERY_SAVER_FPS: f64 = 30.0;
+const BATTERY_SAVER_MENU_FPS: f64 = 15.0;
+
+const BUG_REPORT_MESSAGE_DURATION: f64 = 3.0;
+const QUIT_FADE_DURATION: f32 = 0.5;
+
 const BOARD_TILES_X: usize = 40;
 
 const BOARD_LEFT: f32 = 0.0;
@@ -31,15 +68,62 @@ struct Resources {
     sound_wall: Sound,
     sound_gold: Sound,
     sound_black: Sound,
+    active_mods: Vec<&'static str>,
+}
+
+// Destructured in Resources::new, so adding a sound here without loading it won't compile
+const RESOURCE_PATHS: [&str; 5] = [
+    "moza-unfinished.wav",
+    "lions.wav",
+    "456563__bumpelsnake__bounce1.wav",
+    "456564__bumpelsnake__bell2.wav",
+    "456565__bumpelsnake__bell1.wav",
+];
+
+#[derive(Default)]
+struct LoadingProgress {
+    loaded: usize,
+    current: Option<&'static str>,
+    failed: Option<String>,
+    overrides: Vec<&'static str>,
+}
+
+async fn load_sound_with_progress(
+    path: &'static str,
+    progress: &Mutex<LoadingProgress>,
+) -> Result<Sound, macroquad::Error> {
+    progress.lock().unwrap().current = Some(path);
+    // Loading doesn't yield on desktop, so give the loading screen a frame to show
+    // which asset is next
+    next_frame().await;
+    let sound = match mods::read_override(path) {
+        Some(bytes) => {
+            progress.lock().unwrap().overrides.push(path);
+            load_sound_from_bytes(&bytes).await
+        }
+        None => load_sound(path).await,
+    };
+    match sound {
+        Ok(sound) => {
+            progress.lock().unwrap().loaded += 1;
+            Ok(sound)
+        }
+        Err(e) => {
+            log::error!("Failed to load {}: {}", path, e);
+            progress.lock().unwrap().failed = Some(format!("Failed to load {}", path));
+            Err(e)
+        }
+    }
 }
 
 impl Resources {
-    async fn new() -> Result<Resources, macroquad::Error> {
-        let theme_music = load_sound("moza-unfinished.wav").await?;
-        let lions = load_sound("lions.wav").await?;
-        let sound_wall = load_sound("456563__bumpelsnake__bounce1.wav").await?;
-        let sound_gold = load_sound("456564__bumpelsnake__bell2.wav").await?;
-        let sound_black = load_sound("456565__bumpelsnake__bell1.wav").await?;
+    async fn new(progress: Arc<Mutex<LoadingProgress>>) -> Result<Resources, macroquad::Error> {
+        let [theme_music, lions, sound_wall, sound_gold, sound_black] = RESOURCE_PATHS;
+        let theme_music = load_sound_with_progress(theme_music, &progress).await?;
+        let lions = load_sound_with_progress(lions, &progress).await?;
+        let sound_wall = load_sound_with_progress(sound_wall, &progress).await?;
+        let sound_gold = load_sound_with_progress(sound_gold, &progress).await?;
+        let sound_black = load_sound_with_progress(sound_black, &progress).await?;
 
         Ok(Resources {
             theme_music,
@@ -47,95 +131,191 @@ impl Resources {
             sound_wall,
             sound_gold,
             sound_black,
+            active_mods: progress.lock().unwrap().overrides.clone(),
         })
     }
 
     pub async fn load() -> Result<(), macroquad::Error> {
+        let progress = Arc::new(Mutex::new(LoadingProgress::default()));
+        let coroutine_progress = progress.clone();
         let resources_loading = start_coroutine(async move {
-            let resources = Resources::new().await.unwrap();
-            storage::store(resources);
+            if let Ok(resources) = Resources::new(coroutine_progress).await {
+                storage::store(resources);
+            }
         });
 
         while !resources_loading.is_done() {
             clear_background(BLACK);
-            let text = format!(
-                "Loading resources {}",
-                ".".repeat(((get_time() * 2.) as usize) % 4)
-            );
-            draw_text(
-                &text,
-                screen_width() / 2. - 160.,
-                screen_height() / 2.,
-                40.,
-                WHITE,
-            );
+            draw_loading_progress(&progress.lock().unwrap());
             next_frame().await;
         }
 
+        if progress.lock().unwrap().failed.is_some() {
+            let failed_at = get_time();
+            while get_time() - failed_at < 5.0 {
+                clear_background(BLACK);
+                draw_loading_progress(&progress.lock().unwrap());
+                next_frame().await;
+            }
+            return Err(macroquad::Error::UnknownError("Failed to load resources"));
+        }
+
         Ok(())
     }
 }
 
-fn oscillating_alpha(base_color: Color, cycles_per_second: f32) -> Color {
-    let alpha = 0.5 * (1.0 + f32::sin(cycles_per_second * get_time() as f32 * PI / 2.0));
+fn draw_loading_progress(progress: &LoadingProgress) {
+    let bar_width = f32::min(layout::percent_of_width(80.0), layout::scaled(400.0));
+    let bar_height = layout::scaled(20.0);
+    let bar_x = screen_width() / 2.0 - bar_width / 2.0;
+    let bar_y = screen_height() / 2.0;
+    let fraction = (progress.loaded as f32 / RESOURCE_PATHS.len() as f32).min(1.0);
+
+    draw_rectangle_lines(bar_x, bar_y, bar_width, bar_height, 2.0, WHITE);
+    draw_rectangle(bar_x, bar_y, bar_width * fraction, bar_height, GOLD);
+
+    let (text, color) = match (&progress.failed, progress.current) {
+        (Some(message), _) => (message.clone(), RED),
+        (None, Some(path)) => (format!("Loading {}", path), WHITE),
+        (None, None) => ("Loading resources".to_string(), WHITE),
+    };
+    let font_size = layout::scaled_font(24);
+    let text_dimensions = measure_text(&text, None, font_size, 1.0);
+    draw_text(
+        &text,
+        screen_width() / 2.0 - text_dimensions.width / 2.0,
+        bar_y - layout::scaled(16.0),
+        font_size as f32,
+        color,
+    );
+}
+
+fn oscillating_alpha(
+    base_color: Color,
+    cycles_per_second: f32,
+    time: f32,
+    reduce_flashing: bool,
+) -> Color {
+    if reduce_flashing {
+        return base_color;
+    }
+    let alpha = 0.5 * (1.0 + f32::sin(cycles_per_second * time * PI / 2.0));
     Color::new(base_color.r, base_color.g, base_color.b, alpha)
 }
 
-fn draw_game_title(board: &Board) {
+fn fade_in(elapsed: f32, fade_time: f32) -> f32 {
+    (elapsed / fade_time).clamp(0.0, 1.0)
+}
+
+fn fade_in_out(elapsed: f32, duration: f32, fade_time: f32) -> f32 {
+    f32::min(
+        fade_in(elapsed, fade_time),
+        fade_in(duration - elapsed, fade_time),
+    )
+}
+
+fn with_alpha(color: Color, alpha: f32) -> Color {
+    Color::new(color.r, color.g, color.b, color.a * alpha)
+}
+
+fn draw_splash(elapsed: f32) {
+    clear_background(BLACK);
+    let text = "Made with macroquad";
+    let font_size = layout::scaled_font(32);
+    let text_dimensions = measure_text(text, None, font_size, 1.0);
+    draw_text(
+        text,
+        screen_width() / 2.0 - text_dimensions.width / 2.0,
+        screen_height() / 2.0,
+        font_size as f32,
+        with_alpha(WHITE, fade_in_out(elapsed, SPLASH_DURATION, 0.5)),
+    );
+}
+
+fn draw_game_title(board: &Board, elapsed: f32, reduce_flashing: bool) {
+    let reveal = fade_in(elapsed, TITLE_REVEAL_DURATION);
+    let slide = (1.0 - reveal) * 40.0;
     {
         let text = "Black";
-        let font_size = 48;
+        let font_size = layout::scaled_font(48);
         let text_dimensions = measure_text(text, None, font_size, 1.0);
         draw_text(
             text,
-            board.x + 25.0,
+            board.x + 25.0 - slide,
             board.y + text_dimensions.height + 25.0,
             font_size as f32,
-            BLACK,
+            with_alpha(BLACK, reveal),
         );
     }
     {
         let text = "Gold";
-        let font_size = 48;
+        let font_size = layout::scaled_font(48);
         let text_dimensions = measure_text(text, None, font_size, 1.0);
         draw_text(
             text,
-            board.x + board.width - text_dimensions.width - 25.0,
+            board.x + board.width - text_dimensions.width - 25.0 + slide,
             board.y + board.height - 25.0,
             font_size as f32,
-            GOLD,
+            with_alpha(GOLD, reveal),
         );
     }
+    if reveal < 1.0 {
+        return;
+    }
     {
-        let text = "Click/tap to start";
-        let font_size = 24;
+        let text = "Click/tap or press Space to start";
+        let font_size = layout::scaled_font(24);
         let text_dimensions = measure_text(text, None, font_size, 1.0);
         draw_text(
             text,
             screen_width() / 2.0 - text_dimensions.width / 2.0,
             board.y + 50.0,
             font_size as f32,
-            oscillating_alpha(BLACK, 3.0),
+            oscillating_alpha(BLACK, 3.0, elapsed, reduce_flashing),
+        );
+    }
+}
+
+fn draw_active_mods(board: &Board, active_mods: &[&str]) {
+    if active_mods.is_empty() {
+        return;
+    }
+    let font_size = layout::scaled_font(18);
+    let line_height = font_size as f32 + 2.0;
+    let top = board.y + board.height / 2.0;
+    draw_text("Active mods:", board.x + 25.0, top, font_size as f32, BLACK);
+    for (i, name) in active_mods.iter().enumerate() {
+        draw_text(
+            name,
+            board.x + 25.0,
+            top + (i + 1) as f32 * line_height,
+            font_size as f32,
+            BLACK,
         );
     }
 }
 
 fn draw_scores(board: &Board) {
-    let gold_score = board.tiles.iter().flatten().filter(|&&t| t).count();
-    let black_score = board.tiles.iter().flatten().filter(|&&t| !t).count();
+    let (gold_score, black_score) = board.scores();
     {
         let text = format!("Gold: {}", gold_score);
-        let font_size = 18;
-        draw_text(&text, board.x + 4.0, board.y - 4.0, font_size as f32, BLACK);
+        let font_size = layout::scaled_font(18);
+        draw_text(
+            &text,
+            board.x + 4.0,
+            board.y - layout::scaled(4.0),
+            font_size as f32,
+            BLACK,
+        );
     }
     {
         let text = format!("Black: {}", black_score);
-        let font_size = 18;
+        let font_size = layout::scaled_font(18);
         let text_dimensions = measure_text(&text, None, font_size, 1.0);
         draw_text(
             &text,
             board.x + board.width - text_dimensions.width - 4.0,
-            board.y - 4.0,
+            board.y - layout::scaled(4.0),
             font_size as f32,
             BLACK,
         );
@@ -207,6 +387,12 @@ impl Board {
         board
     }
 
+    fn scores(&self) -> (usize, usize) {
+        let gold_score = self.tiles.iter().flatten().filter(|&&t| t).count();
+        let black_score = self.tiles.iter().flatten().filter(|&&t| !t).count();
+        (gold_score, black_score)
+    }
+
     fn tile_width(&self) -> f32 {
         self.width / BOARD_TILES_X as f32
     }
@@ -225,7 +411,7 @@ impl Board {
     }
 
     fn update_size_and_position(&mut self) {
-        self.width = f32::min(screen_width(), screen_height() - 40.0);
+        self.width = f32::min(screen_width(), screen_height() - layout::scaled(40.0));
         self.height = self.tile_width() * (BOARD_TILES_X - 1) as f32;
         self.x = screen_width() / 2.0 - self.width / 2.0;
         self.y = screen_height() / 2.0 - self.height / 2.0;
@@ -263,13 +449,116 @@ fn draw_board(board: &Board, balls: &mut [Ball]) {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum GameState {
+    Splash,
     Starting,
     Playing,
 }
 
-fn move_ball(board: &mut Board, ball: &mut Ball, wall_sound: &Sound, bounce_volume: f32) {
-    let frame_time = get_frame_time().min(0.0035);
+fn frame_rate_limit(
+    frame_cap: FrameCap,
+    battery_saver: bool,
+    game_state: GameState,
+) -> Option<f64> {
+    if !battery_saver {
+        return frame_cap.fps();
+    }
+    // Menus only animate the title, so they can run slower still
+    let limit = if game_state == GameState::Playing {
+        BATTERY_SAVER_FPS
+    } else {
+        BATTERY_SAVER_MENU_FPS
+    };
+    Some(frame_cap.fps().map_or(limit, |fps| fps.min(limit)))
+}
+
+/// Fades to black, switches state at the midpoint, then fades back in.
+struct Transition {
+    to: GameState,
+    started_at: f64,
+    switched: bool,
+}
+
+impl Transition {
+    fn to(to: GameState, now: f64) -> Self {
+        Self {
+            to,
+            started_at: now,
+            switched: false,
+        }
+    }
+
+    fn elapsed(&self, now: f64) -> f32 {
+        (now - self.started_at) as f32
+    }
+}
+
+fn update_transition(
+    transition: &mut Option<Transition>,
+    game_state: &mut GameState,
+    state_entered_at: &mut f64,
+    now: f64,
+) {
+    if let Some(t) = transition.as_mut() {
+        let elapsed = t.elapsed(now);
+        if !t.switched && elapsed >= TRANSITION_DURATION / 2.0 {
+            *game_state = t.to;
+            *state_entered_at = now;
+            t.switched = true;
+        }
+        if elapsed >= TRANSITION_DURATION {
+            *transition = None;
+        }
+    }
+}
+
+fn draw_transition(transition: &Option<Transition>, now: f64) {
+    if let Some(t) = transition {
+        let half = TRANSITION_DURATION / 2.0;
+        let alpha = 1.0 - ((t.elapsed(now) - half).abs() / half).min(1.0);
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            with_alpha(BLACK, alpha),
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum GameSpeed {
+    Slow,
+    Reduced,
+    Normal,
+}
+
+impl GameSpeed {
+    fn time_scale(self) -> f32 {
+        match self {
+            GameSpeed::Slow => 0.75,
+            GameSpeed::Reduced => 0.9,
+            GameSpeed::Normal => 1.0,
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            GameSpeed::Slow => GameSpeed::Reduced,
+            GameSpeed::Reduced => GameSpeed::Normal,
+            GameSpeed::Normal => GameSpeed::Slow,
+        }
+    }
+}
+
+fn move_ball(
+    board: &mut Board,
+    ball: &mut Ball,
+    wall_sound: &Sound,
+    bounce_volume: f32,
+    frame_time: f32,
+) {
     let movement = MOVEMENT_SPEED * ball.speed * frame_time;
     let p_radius = ball.size / 2.0;
     let radius = p_radius / board.width;
@@ -393,10 +682,129 @@ fn move_ball(board: &mut Board, ball: &mut Ball, wall_sound: &Sound, bounce_volu
     ball.y = new_y;
 }
 
-fn draw_toggle_button(position: Vec2, text: &str, toggle: &mut bool) -> bool {
-    // Draw the button text
-    let font_size = 21;
+/// Saves a bug report when F12 is pressed and shows the result for a few seconds.
+fn update_bug_report(seed: u64, now: f64, message: &mut Option<(String, f64)>) {
+    if is_key_pressed(KeyCode::F12) {
+        *message = Some((bug_report::capture(seed), now));
+    }
+    if let Some((message, shown_at)) = message {
+        if now - *shown_at < BUG_REPORT_MESSAGE_DURATION {
+            draw_text(
+                message,
+                layout::scaled(4.0),
+                screen_height() - layout::scaled(24.0),
+                layout::scaled(18.0),
+                WHITE,
+            );
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum QuitState {
+    Running,
+    Confirming { quit_selected: bool },
+    Quitting { since: f64 },
+}
+
+impl QuitState {
+    /// Opens the quit dialog with "No" focused.
+    fn confirming() -> Self {
+        // Drop letters typed before the dialog opened so they can't answer it
+        clear_input_queue();
+        QuitState::Confirming {
+            quit_selected: false,
+        }
+    }
+}
+
+/// Returns `Some(true)` to quit and `Some(false)` to keep playing. Left and right
+/// move `quit_selected` between the two buttons.
+fn draw_quit_dialog(quit_selected: &mut bool) -> Option<bool> {
+    draw_rectangle(
+        0.0,
+        0.0,
+        screen_width(),
+        screen_height(),
+        Color::new(0.0, 0.0, 0.0, 0.6),
+    );
+    let text = format!("Quit {}?", branding::GAME_TITLE);
+    let font_size = layout::scaled_font(32);
     let text_dimensions = measure_text(&text, None, font_size, 1.0);
+    draw_text(
+        &text,
+        screen_width() / 2.0 - text_dimensions.width / 2.0,
+        screen_height() / 2.0 - layout::scaled(20.0),
+        font_size as f32,
+        WHITE,
+    );
+
+    let labels = ["Yes (Y)", "No (N)"];
+    let font_size = layout::scaled_font(21);
+    let widths: Vec<f32> = labels
+        .iter()
+        .map(|label| measure_text(label, None, font_size, 1.0).width)
+        .collect();
+    let (xs, _) = layout::centered_row(
+        &widths,
+        layout::scaled(24.0),
+        screen_width() / 2.0,
+        screen_width(),
+    );
+    let y = screen_height() / 2.0 + layout::scaled(24.0);
+    *quit_selected = navigate_menu(Some(*quit_selected), &[true, false]) == Some(true);
+    let yes = draw_button(Vec2::new(xs[0], y), labels[0], font_size, *quit_selected);
+    let no = draw_button(Vec2::new(xs[1], y), labels[1], font_size, !*quit_selected);
+
+    // Match the typed character rather than the key code, which is the physical
+    // key position on some platforms
+    let typed: Vec<char> = std::iter::from_fn(get_char_pressed)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    if yes || typed.contains(&'y') {
+        Some(true)
+    } else if no || typed.contains(&'n') || is_key_pressed(KeyCode::Escape) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MenuButton {
+    FrameCap,
+    BatterySaver,
+    Flashing,
+    Music,
+    SoundFx,
+    Speed,
+}
+
+fn navigate_menu<T: Copy + PartialEq>(selected: Option<T>, buttons: &[T]) -> Option<T> {
+    if buttons.is_empty() || is_key_pressed(KeyCode::Escape) {
+        return None;
+    }
+    let step: isize = if is_key_pressed(KeyCode::Right)
+        || is_key_pressed(KeyCode::Down)
+        || is_key_pressed(KeyCode::Tab)
+    {
+        1
+    } else if is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::Up) {
+        -1
+    } else {
+        return selected.filter(|button| buttons.contains(button));
+    };
+    let index = match selected.and_then(|button| buttons.iter().position(|&b| b == button)) {
+        Some(index) => (index as isize + step).rem_euclid(buttons.len() as isize) as usize,
+        None if step > 0 => 0,
+        None => buttons.len() - 1,
+    };
+    Some(buttons[index])
+}
+
+fn draw_button(position: Vec2, text: &str, font_size: u16, selected: bool) -> bool {
+    // Draw the button text
+    let text_dimensions = measure_text(text, None, font_size, 1.0);
     let hitbox = Rect::new(
         position.x - 2.0,
         position.y - text_dimensions.height - 2.0,
@@ -404,34 +812,68 @@ fn draw_toggle_button(position: Vec2, text: &str, toggle: &mut bool) -> bool {
         text_dimensions.height + 4.0,
     );
     draw_rectangle(hitbox.x, hitbox.y, hitbox.w, hitbox.h, BLACK);
+    if selected {
+        draw_rectangle_lines(hitbox.x, hitbox.y, hitbox.w, hitbox.h, 2.0, WHITE);
+    }
     draw_text(text, position.x, position.y, font_size as f32, GOLD);
     let (mouse_x, mouse_y) = mouse_position();
-    if is_mouse_button_pressed(MouseButton::Left)
+    let clicked = is_mouse_button_pressed(MouseButton::Left)
         && mouse_x >= hitbox.x
         && mouse_x <= hitbox.x + hitbox.w
         && mouse_y >= hitbox.y
-        && mouse_y <= hitbox.y + hitbox.h
-    {
-        *toggle = !*toggle;
-        return true;
+        && mouse_y <= hitbox.y + hitbox.h;
+    clicked || (selected && is_key_pressed(KeyCode::Enter))
+}
+
+/// Draws `buttons` as a row centered on `center_x`, shrinking it to fit `max_width`.
+/// Returns the button that was pressed, if any.
+fn draw_button_row(
+    buttons: &[(MenuButton, String)],
+    center_x: f32,
+    y: f32,
+    max_width: f32,
+    selected: Option<MenuButton>,
+) -> Option<MenuButton> {
+    let font_size = layout::scaled_font(21);
+    let widths: Vec<f32> = buttons
+        .iter()
+        .map(|(_, text)| measure_text(text, None, font_size, 1.0).width)
+        .collect();
+    let (xs, fit) = layout::centered_row(&widths, layout::scaled(16.0), center_x, max_width);
+    let font_size = (font_size as f32 * fit) as u16;
+
+    let mut pressed = None;
+    for ((button, text), x) in buttons.iter().zip(xs) {
+        if draw_button(Vec2::new(x, y), text, font_size, selected == Some(*button)) {
+            pressed = Some(*button);
+        }
     }
+    pressed
+}
 
-    false
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "On"
+    } else {
+        "Off"
+    }
 }
-#[macroquad::main("Black and Gold", Conf {
-    // sample_count: 4,
-    ..Default::default()
-})]
+
+#[macroquad::main(window_conf)]
 async fn main() -> Result<(), macroquad::Error> {
-    rand::srand(miniquad::date::now() as u64);
+    let seed = miniquad::date::now() as u64;
+    rand::srand(seed);
 
     simple_logger::setup_logger();
+    crash::install_panic_hook();
+    log::info!("Seed: {}", seed);
 
     log::info!("¡Viva la libertad, Carajo!");
 
     set_pc_assets_folder("assets");
 
     Resources::load().await?;
+    prevent_quit();
     let resources = storage::get::<Resources>();
 
     let black_balls: Vec<Ball> = (0..NUM_BLACK_BALLS)
@@ -464,16 +906,82 @@ async fn main() -> Result<(), macroquad::Error> {
 
     let mut board = Board::new();
 
-    let mut game_state = GameState::Starting;
+    let mut recorder = FrameRecorder::from_args();
+
+    let mut game_state = GameState::Splash;
+    let mut state_entered_at = recorder.as_ref().map_or_else(get_time, FrameRecorder::time);
+    let mut transition: Option<Transition> = None;
 
     let mut music_on = true;
     let mut sound_on = NUM_BLACK_BALLS + NUM_GOLD_BALLS < 10;
+    let mut game_speed = GameSpeed::Normal;
+    let mut flashing_on = true;
+    let mut frame_cap = FrameCap::Uncapped;
+    let mut battery_saver = false;
+    let mut frame_pacer = FramePacer::new();
+    let mut bug_report_message: Option<(String, f64)> = None;
+    let mut quit_state = QuitState::Running;
+
+    #[cfg(debug_assertions)]
+    let mut show_console = false;
+    #[cfg(debug_assertions)]
+    let mut debug_time = debug_time::DebugTime::default();
 
     let mut started_music = false;
     let mut started_lions = false;
     let mut lions_start_time = None;
 
+    let mut selected_button = None;
+
     loop {
+        // Recordings advance a fixed amount per frame, however long capturing takes
+        let now = recorder.as_ref().map_or_else(get_time, FrameRecorder::time);
+        update_transition(&mut transition, &mut game_state, &mut state_entered_at, now);
+
+        #[cfg(debug_assertions)]
+        debug_time.update();
+
+        let (gold_score, black_score) = board.scores();
+        crash::set_game_summary(format!(
+            "state: {:?}, gold: {}, black: {}, balls: {}",
+            game_state,
+            gold_score,
+            black_score,
+            balls.len()
+        ));
+
+        if game_state == GameState::Splash && is_quit_requested() {
+            // Cut the splash short so the quit dialog shows over the title screen
+            game_state = GameState::Starting;
+            state_entered_at = now;
+            transition = None;
+            quit_state = QuitState::confirming();
+        }
+
+        if let GameState::Splash = game_state {
+            let elapsed = (now - state_entered_at) as f32;
+            draw_splash(elapsed);
+            if transition.is_none()
+                && (elapsed >= SPLASH_DURATION
+                    || get_keys_pressed().iter().any(|&key| key != KeyCode::F12)
+                    || is_mouse_button_pressed(MouseButton::Left))
+            {
+                transition = Some(Transition::to(GameState::Starting, now));
+            }
+            draw_transition(&transition, now);
+            if recorder
+                .as_mut()
+                .is_some_and(|recorder| !recorder.capture())
+            {
+                log::error!("Recording stopped");
+                recorder = None;
+            }
+            update_bug_report(seed, now, &mut bug_report_message);
+            frame_pacer.wait(frame_rate_limit(frame_cap, battery_saver, game_state));
+            next_frame().await;
+            continue;
+        }
+
         clear_background(Color::new(116.0 / 255.0, 172.0 / 255.0, 223.0 / 255.0, 1.0));
 
         board.update_size_and_position();
@@ -484,22 +992,67 @@ async fn main() -> Result<(), macroquad::Error> {
 
         draw_scores(&board);
 
+        let top_buttons = [
+            (MenuButton::FrameCap, format!("FPS: {}", frame_cap.label())),
+            (
+                MenuButton::BatterySaver,
+                format!("Battery saver: {}", on_off(battery_saver)),
+            ),
+        ];
+        let mut bottom_buttons = vec![(
+            MenuButton::Flashing,
+            format!("Flashing: {}", on_off(flashing_on)),
+        )];
         if started_music {
-            draw_toggle_button(
-                Vec2::new(
-                    board.x + board.width / 2.0 - 100.0,
-                    board.y + board.height + 16.0,
-                ),
-                &format!("Music: {}", if music_on { "On" } else { "Off" }),
-                &mut music_on,
-            );
+            bottom_buttons.push((MenuButton::Music, format!("Music: {}", on_off(music_on))));
+        }
+        bottom_buttons.push((
+            MenuButton::SoundFx,
+            format!("Sound Fx: {}", on_off(sound_on)),
+        ));
+        bottom_buttons.push((
+            MenuButton::Speed,
+            format!("Speed: {}%", (game_speed.time_scale() * 100.0).round()),
+        ));
+
+        let menu_buttons: Vec<MenuButton> = top_buttons
+            .iter()
+            .chain(&bottom_buttons)
+            .map(|&(button, _)| button)
+            .collect();
+        let previous_selection = selected_button;
+        if quit_state == QuitState::Running && transition.is_none() {
+            selected_button = navigate_menu(selected_button, &menu_buttons);
         }
 
-        draw_toggle_button(
-            Vec2::new(board.x + board.width / 2.0, board.y + board.height + 16.0),
-            &format!("Sound Fx: {}", if sound_on { "On" } else { "Off" }),
-            &mut sound_on,
+        let center_x = board.x + board.width / 2.0;
+        // The scores sit in the top corners, so the top row gets the middle of the board
+        let top_pressed = draw_button_row(
+            &top_buttons,
+            center_x,
+            board.y - layout::scaled(4.0),
+            board.width * 0.6,
+            selected_button,
         );
+        let bottom_pressed = draw_button_row(
+            &bottom_buttons,
+            center_x,
+            board.y + board.height + layout::scaled(16.0),
+            screen_width() - layout::scaled(8.0),
+            selected_button,
+        );
+        let pressed = top_pressed
+            .or(bottom_pressed)
+            .filter(|_| quit_state == QuitState::Running && transition.is_none());
+        match pressed {
+            Some(MenuButton::FrameCap) => frame_cap = frame_cap.next(),
+            Some(MenuButton::BatterySaver) => battery_saver = !battery_saver,
+            Some(MenuButton::Flashing) => flashing_on = !flashing_on,
+            Some(MenuButton::Music) => music_on = !music_on,
+            Some(MenuButton::SoundFx) => sound_on = !sound_on,
+            Some(MenuButton::Speed) => game_speed = game_speed.next(),
+            None => {}
+        }
 
         if music_on {
             set_sound_volume(&resources.theme_music, 1.0);
@@ -507,10 +1060,15 @@ async fn main() -> Result<(), macroquad::Error> {
             set_sound_volume(&resources.theme_music, 0.0);
         }
 
-        if is_mouse_button_pressed(MouseButton::Left) {
+        if quit_state == QuitState::Running
+            && transition.is_none()
+            && (is_mouse_button_pressed(MouseButton::Left)
+                || is_key_pressed(KeyCode::Space)
+                || (selected_button.is_none() && is_key_pressed(KeyCode::Enter)))
+        {
             if !started_lions {
                 started_lions = true;
-                lions_start_time = Some(get_time());
+                lions_start_time = Some(now);
                 play_sound(
                     &resources.lions,
                     PlaySoundParams {
@@ -519,11 +1077,13 @@ async fn main() -> Result<(), macroquad::Error> {
                     },
                 );
             }
-            game_state = GameState::Playing;
+            if game_state == GameState::Starting && transition.is_none() {
+                transition = Some(Transition::to(GameState::Playing, now));
+            }
         }
         if !started_music {
             if let Some(start_time) = lions_start_time {
-                if get_time() - start_time >= 15.0 {
+                if now - start_time >= 15.0 {
                     started_music = true;
                     play_sound(
                         &resources.theme_music,
@@ -536,23 +1096,110 @@ async fn main() -> Result<(), macroquad::Error> {
             }
         }
         match game_state {
+            GameState::Splash => {}
             GameState::Starting => {
                 draw_board(&board, &mut balls[..]);
-                draw_game_title(&board);
+                draw_game_title(&board, (now - state_entered_at) as f32, !flashing_on);
+                draw_active_mods(&board, &resources.active_mods);
             }
             GameState::Playing => {
-                for ball in balls.iter_mut() {
-                    move_ball(
-                        &mut board,
-                        ball,
-                        &resources.sound_wall,
-                        if sound_on { 0.05 } else { 0.0 },
-                    );
+                // Recording runs at a fixed step so slow machines still produce smooth footage
+                let real_frame_time = if recorder.is_some() {
+                    1.0 / REFERENCE_FPS
+                } else {
+                    get_frame_time().min(MAX_REAL_FRAME_TIME)
+                };
+                let simulated_time = real_frame_time * SIMULATION_RATE * game_speed.time_scale();
+                #[cfg(debug_assertions)]
+                let simulated_time = debug_time.scale(simulated_time);
+                #[cfg(not(debug_assertions))]
+                let simulated_time = Some(simulated_time);
+                let mut remaining = simulated_time
+                    .filter(|_| quit_state == QuitState::Running)
+                    .unwrap_or(0.0);
+                while remaining > 0.0 {
+                    let step = remaining.min(MAX_FRAME_TIME);
+                    for ball in balls.iter_mut() {
+                        move_ball(
+                            &mut board,
+                            ball,
+                            &resources.sound_wall,
+                            if sound_on { 0.05 } else { 0.0 },
+                            step,
+                        );
+                    }
+                    remaining -= step;
                 }
                 draw_board(&board, &mut balls[..]);
             }
         }
 
+        #[cfg(debug_assertions)]
+        {
+            if is_key_pressed(KeyCode::F4) {
+                show_console = !show_console;
+            }
+            if show_console {
+                if is_key_pressed(KeyCode::F5) {
+                    console::cycle_log_level();
+                }
+                console::draw_log_console();
+            }
+            debug_time.draw_status();
+        }
+
+        match quit_state {
+            QuitState::Running => {
+                let escape_pressed = cfg!(not(target_arch = "wasm32"))
+                    && previous_selection.is_none()
+                    && is_key_pressed(KeyCode::Escape);
+                if is_quit_requested() || escape_pressed {
+                    quit_state = QuitState::confirming();
+                }
+            }
+            QuitState::Confirming { mut quit_selected } => {
+                quit_state = match draw_quit_dialog(&mut quit_selected) {
+                    Some(true) => QuitState::Quitting { since: now },
+                    Some(false) => QuitState::Running,
+                    None => QuitState::Confirming { quit_selected },
+                }
+            }
+            QuitState::Quitting { since } => {
+                // Fade the audio and screen out rather than cutting off mid-sound
+                let remaining = 1.0 - (now - since) as f32 / QUIT_FADE_DURATION;
+                if remaining <= 0.0 {
+                    stop_sound(&resources.theme_music);
+                    stop_sound(&resources.lions);
+                    return Ok(());
+                }
+                set_sound_volume(
+                    &resources.theme_music,
+                    if music_on { remaining } else { 0.0 },
+                );
+                set_sound_volume(&resources.lions, remaining);
+                draw_rectangle(
+                    0.0,
+                    0.0,
+                    screen_width(),
+                    screen_height(),
+                    with_alpha(BLACK, 1.0 - remaining),
+                );
+            }
+        }
+
+        draw_transition(&transition, now);
+
+        if recorder
+            .as_mut()
+            .is_some_and(|recorder| !recorder.capture())
+        {
+            log::error!("Recording stopped");
+            recorder = None;
+        }
+
+        update_bug_report(seed, now, &mut bug_report_message);
+
+        frame_pacer.wait(frame_rate_limit(frame_cap, battery_saver, game_state));
         next_frame().await
     }
 }
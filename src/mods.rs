@@ -0,0 +1,40 @@
+use std::path::Path;
+
+const MODS_DIR: &str = "mods";
+
+/// Whether `bytes` look like the format of the bundled asset `name`. The audio
+/// decoder panics on files it can't parse, so anything else is rejected up front.
+fn matches_format(name: &str, bytes: &[u8]) -> bool {
+    match Path::new(name).extension().and_then(|ext| ext.to_str()) {
+        Some("wav") => bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE",
+        Some("ogg") => bytes.starts_with(b"OggS"),
+        _ => false,
+    }
+}
+
+/// Contents of `mods/<name>` under the working directory if the player dropped in a
+/// replacement for a bundled asset. Overrides that can't be read or aren't in the
+/// bundled asset's format are skipped so the bundled asset is used instead.
+pub fn read_override(name: &str) -> Option<Vec<u8>> {
+    let path = Path::new(MODS_DIR).join(name);
+    if !path.is_file() {
+        return None;
+    }
+    match std::fs::read(&path) {
+        Ok(bytes) if !matches_format(name, &bytes) => {
+            log::warn!(
+                "Ignoring mod override {}: not in the same format as the bundled asset",
+                path.display()
+            );
+            None
+        }
+        Ok(bytes) => {
+            log::info!("Using mod override {}", path.display());
+            Some(bytes)
+        }
+        Err(e) => {
+            log::warn!("Ignoring unreadable mod override {}: {}", path.display(), e);
+            None
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+
+const DEFAULT_RECORDING_DIR: &str = "recording";
+
+pub struct FrameRecorder {
+    dir: PathBuf,
+    frame: usize,
+}
+
+impl FrameRecorder {
+    /// Enabled with `--record [dir]`. Frames are written as numbered PNGs.
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args().skip_while(|arg| arg != "--record");
+        args.next()?;
+        let dir = PathBuf::from(
+            args.next()
+                .filter(|arg| !arg.starts_with("--"))
+                .unwrap_or_else(|| DEFAULT_RECORDING_DIR.to_string()),
+        );
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::error!("Could not create recording dir {}: {}", dir.display(), e);
+            return None;
+        }
+        log::info!("Recording frames to {}", dir.display());
+
+        Some(Self { dir, frame: 0 })
+    }
+
+    /// Seconds of footage recorded so far, used as the game clock while recording.
+    pub fn time(&self) -> f64 {
+        self.frame as f64 / crate::REFERENCE_FPS as f64
+    }
+
+    /// Saves the current frame. Returns `false` if it couldn't be written, in which
+    /// case recording should stop.
+    pub fn capture(&mut self) -> bool {
+        let path = self.dir.join(format!("frame_{:06}.png", self.frame));
+        // export_png unwraps its write, so make sure the file can be created first
+        if let Err(e) = std::fs::File::create(&path) {
+            log::error!("Could not write frame to {}: {}", path.display(), e);
+            return false;
+        }
+        get_screen_data().export_png(&path.to_string_lossy());
+        self.frame += 1;
+        true
+    }
+}
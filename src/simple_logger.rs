@@ -1,20 +1,122 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
 use log::Log;
 use log::{Level, LevelFilter, Metadata, Record};
 #[cfg(target_os = "ios")]
 use oslog::OsLogger;
 
-pub struct SimpleLogger;
+const RECENT_RECORDS_CAPACITY: usize = 100;
+#[cfg(not(target_os = "ios"))]
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+#[cfg(target_os = "ios")]
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Debug;
+
+#[derive(Clone)]
+pub struct LogLine {
+    pub level: Level,
+    pub message: String,
+}
+
+impl fmt::Display for LogLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {}", self.level, self.message)
+    }
+}
+
+lazy_static! {
+    static ref RECENT_RECORDS: Mutex<VecDeque<LogLine>> =
+        Mutex::new(VecDeque::with_capacity(RECENT_RECORDS_CAPACITY));
+    static ref MODULE_LEVELS: Mutex<Vec<(String, LevelFilter)>> = Mutex::new(Vec::new());
+}
+
+/// The last few log lines, oldest first. Skips if the buffer is locked, e.g. when
+/// called from a panic raised while logging.
+pub fn recent_records() -> Vec<String> {
+    match RECENT_RECORDS.try_lock() {
+        Ok(records) => records.iter().map(|line| line.to_string()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// The last `count` log lines with their levels, oldest first.
+#[cfg(debug_assertions)]
+pub fn tail(count: usize) -> Vec<LogLine> {
+    let records = RECENT_RECORDS.lock().unwrap();
+    let skip = records.len().saturating_sub(count);
+    records.iter().skip(skip).cloned().collect()
+}
+
+/// Overrides the level for `module` and everything below it, e.g. `bg::recorder`.
+pub fn set_module_level(module: &str, level: LevelFilter) {
+    let mut levels = MODULE_LEVELS.lock().unwrap();
+    levels.retain(|(m, _)| m != module);
+    levels.push((module.to_string(), level));
+    // Longest prefix first so the most specific module wins
+    levels.sort_by_key(|(m, _)| std::cmp::Reverse(m.len()));
+    let max_level = levels.iter().map(|&(_, l)| l).fold(DEFAULT_LEVEL, Ord::max);
+    log::set_max_level(max_level);
+}
+
+/// The level that applies to log records from `target`.
+pub fn level_for(target: &str) -> LevelFilter {
+    MODULE_LEVELS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(module, _)| {
+            target
+                .strip_prefix(module.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        })
+        .map_or(DEFAULT_LEVEL, |&(_, level)| level)
+}
+
+/// Reads `module=level` pairs from `BG_LOG`, e.g. `BG_LOG=bg=debug,bg::crash=trace`.
+fn apply_env_levels() {
+    let Ok(spec) = std::env::var("BG_LOG") else {
+        return;
+    };
+    for directive in spec.split(',').filter(|d| !d.is_empty()) {
+        match directive
+            .split_once('=')
+            .and_then(|(module, level)| Some((module, level.parse().ok()?)))
+        {
+            Some((module, level)) => set_module_level(module, level),
+            None => log::warn!("Ignoring invalid BG_LOG directive: {}", directive),
+        }
+    }
+}
+
+/// Prints records (to the unified log on iOS) and keeps the most recent ones for
+/// crash and bug reports.
+pub struct SimpleLogger {
+    #[cfg(target_os = "ios")]
+    os_logger: OsLogger,
+}
 
 impl Log for SimpleLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Info
+        metadata.level() <= level_for(metadata.target())
     }
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            {
-                println!("{} - {}", record.level(), record.args());
+            let line = LogLine {
+                level: record.level(),
+                message: record.args().to_string(),
+            };
+            #[cfg(not(target_os = "ios"))]
+            println!("{}", line);
+            #[cfg(target_os = "ios")]
+            self.os_logger.log(record);
+            let mut records = RECENT_RECORDS.lock().unwrap();
+            if records.len() == RECENT_RECORDS_CAPACITY {
+                records.pop_front();
             }
+            records.push_back(line);
         }
     }
 
@@ -22,16 +124,12 @@ impl Log for SimpleLogger {
 }
 
 pub fn setup_logger() {
-    #[cfg(not(target_os = "ios"))]
-    log::set_boxed_logger(Box::new(SimpleLogger))
-        .map(|()| log::set_max_level(LevelFilter::Info))
+    let logger = SimpleLogger {
+        #[cfg(target_os = "ios")]
+        os_logger: OsLogger::new("news.bg.BG"),
+    };
+    log::set_boxed_logger(Box::new(logger))
+        .map(|()| log::set_max_level(DEFAULT_LEVEL))
         .expect("Failed to set logger");
-
-    #[cfg(target_os = "ios")]
-    {
-        OsLogger::new("news.bg.BG")
-            .level_filter(LevelFilter::Debug)
-            .init()
-            .unwrap();
-    }
+    apply_env_levels();
 }